@@ -0,0 +1,237 @@
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Once, OnceLock};
+use std::thread;
+
+// An Actor owns its state exclusively; events arrive one at a time through
+// `on_event`, dispatched by whichever pool worker happens to drain its
+// mailbox next.
+pub trait Actor: Send + 'static {
+    type Event: Send + 'static;
+    fn on_event(&mut self, event: Self::Event);
+}
+
+// --- lock-free MPSC mailbox (D. Vyukov's intrusive queue) ------------------
+//
+// Nodes are the boxed events themselves: a producer atomically swaps its
+// node onto `tail`, then patches the predecessor's `next` so the single
+// consumer can reach it. The only benign race is a consumer finding `next`
+// still null because a producer has claimed `tail` but hasn't linked it yet;
+// `pop` then returns nothing, and the draining worker, seeing `tail` already
+// past `head`, spins briefly until the link lands.
+
+struct Node<E> {
+    next: AtomicPtr<Node<E>>,
+    event: UnsafeCell<Option<E>>,
+}
+
+struct Mailbox<E> {
+    head: AtomicPtr<Node<E>>,
+    tail: AtomicPtr<Node<E>>,
+}
+unsafe impl<E: Send> Send for Mailbox<E> {}
+unsafe impl<E: Send> Sync for Mailbox<E> {}
+impl<E> Mailbox<E> {
+    fn new() -> Self {
+        let stub = Box::into_raw(Box::new(Node {
+            next: AtomicPtr::new(ptr::null_mut()),
+            event: UnsafeCell::new(None),
+        }));
+        Mailbox {
+            head: AtomicPtr::new(stub),
+            tail: AtomicPtr::new(stub),
+        }
+    }
+    fn push(&self, event: E) {
+        let node = Box::into_raw(Box::new(Node {
+            next: AtomicPtr::new(ptr::null_mut()),
+            event: UnsafeCell::new(Some(event)),
+        }));
+        let prev = self.tail.swap(node, Ordering::AcqRel);
+        unsafe { (*prev).next.store(node, Ordering::Release) };
+    }
+    fn pop(&self) -> Option<E> {
+        let head = self.head.load(Ordering::Acquire);
+        let next = unsafe { (*head).next.load(Ordering::Acquire) };
+        if next.is_null() {
+            return None; // empty, or a producer is mid-link; caller retries later
+        }
+        let event = unsafe { (*next).event.get().as_mut().unwrap().take() };
+        self.head.store(next, Ordering::Release);
+        unsafe { drop(Box::from_raw(head)) };
+        event
+    }
+    // non-consuming emptiness check: safe to call from a thread that does not
+    // (yet) hold the exclusive right to `pop`, since it only compares the two
+    // pointers and never dereferences a node that `pop` may free. A push whose
+    // link is still pending counts as non-empty.
+    fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire) == self.tail.load(Ordering::Acquire)
+    }
+}
+impl<E> Drop for Mailbox<E> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+        unsafe { drop(Box::from_raw(self.head.load(Ordering::Acquire))) };
+    }
+}
+
+// --- shared worker pool ------------------------------------------------
+//
+// One pool is shared by every actor in the process instead of a dedicated
+// OS thread per actor. Workers park on the condvar when the ready-queue is
+// empty and are woken as soon as an actor is scheduled.
+
+trait Scheduled: Send + Sync {
+    fn drain(self: Arc<Self>);
+}
+
+struct Pool {
+    queue: Mutex<VecDeque<Arc<dyn Scheduled>>>,
+    cv: Condvar,
+}
+impl Pool {
+    fn get() -> &'static Pool {
+        static POOL: OnceLock<Pool> = OnceLock::new();
+        static STARTED: Once = Once::new();
+        let pool = POOL.get_or_init(|| Pool {
+            queue: Mutex::new(VecDeque::new()),
+            cv: Condvar::new(),
+        });
+        STARTED.call_once(|| {
+            let workers = thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4);
+            for _ in 0..workers {
+                thread::spawn(Pool::worker_loop);
+            }
+        });
+        pool
+    }
+    fn enqueue(&self, cell: Arc<dyn Scheduled>) {
+        let mut queue = self.queue.lock().expect("pool queue poisoned");
+        queue.push_back(cell);
+        self.cv.notify_one();
+    }
+    fn worker_loop() {
+        let pool = Pool::get();
+        loop {
+            let cell = {
+                let mut queue = pool.queue.lock().expect("pool queue poisoned");
+                while queue.is_empty() {
+                    queue = pool.cv.wait(queue).expect("pool queue poisoned");
+                }
+                queue.pop_front().expect("queue checked non-empty")
+            };
+            cell.drain();
+        }
+    }
+}
+
+// --- per-actor mailbox + scheduling state -------------------------------
+
+struct ActorCell<A: Actor> {
+    mailbox: Mailbox<A::Event>,
+    actor: UnsafeCell<A>,
+    scheduled: AtomicBool, // true while some worker owns the right to drain us
+}
+// Safety: `actor` is only ever touched by the single worker that holds the
+// `scheduled` claim (see `schedule`/`drain`), so concurrent access never
+// happens despite the UnsafeCell.
+unsafe impl<A: Actor> Sync for ActorCell<A> {}
+impl<A: Actor> ActorCell<A> {
+    fn schedule(self: &Arc<Self>) {
+        if self
+            .scheduled
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            Pool::get().enqueue(self.clone());
+        }
+    }
+}
+impl<A: Actor> Scheduled for ActorCell<A> {
+    fn drain(self: Arc<Self>) {
+        loop {
+            match self.mailbox.pop() {
+                // Safety: see the `unsafe impl Sync` note above.
+                Some(event) => unsafe { (*self.actor.get()).on_event(event) },
+                None => {
+                    self.scheduled.store(false, Ordering::Release);
+                    // an event may have landed just after our last pop and
+                    // before we cleared `scheduled`. Peek rather than pop: a
+                    // producer could have already won the race and CAS'd
+                    // `scheduled` back to true, handing this cell to another
+                    // worker, in which case `pop` is no longer ours to call.
+                    if self.mailbox.is_empty() {
+                        return;
+                    }
+                    // reclaim the claim before touching the mailbox again, so
+                    // at most one worker is ever calling `pop` at a time.
+                    if self
+                        .scheduled
+                        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                    {
+                        continue; // we alone own `pop` again; resume draining
+                    }
+                    return; // another worker reclaimed us; it owns `pop` now
+                }
+            }
+        }
+    }
+}
+
+trait Mailable<E>: Send + Sync {
+    fn send_event(self: Arc<Self>, event: E);
+}
+impl<A: Actor> Mailable<A::Event> for ActorCell<A> {
+    fn send_event(self: Arc<Self>, event: A::Event) {
+        self.mailbox.push(event);
+        self.schedule();
+    }
+}
+
+// --- public capability handle -------------------------------------------
+
+// A `Cap<E>` is a cloneable, sendable capability to deliver `E` events to
+// the actor it was created for, without that actor's concrete type being
+// visible.
+pub struct Cap<E: Send + 'static> {
+    sink: Arc<dyn Mailable<E>>,
+}
+impl<E: Send + 'static> Cap<E> {
+    pub fn send(&self, event: E) {
+        self.sink.clone().send_event(event);
+    }
+}
+impl<E: Send + 'static> Clone for Cap<E> {
+    fn clone(&self) -> Self {
+        Cap {
+            sink: self.sink.clone(),
+        }
+    }
+}
+impl<E: Send + 'static> PartialEq for Cap<E> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.sink, &other.sink)
+    }
+}
+impl<E: Send + 'static> Eq for Cap<E> {}
+impl<E: Send + 'static> fmt::Debug for Cap<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cap(..)")
+    }
+}
+
+pub fn create<A: Actor>(actor: A) -> Cap<A::Event> {
+    let cell: Arc<ActorCell<A>> = Arc::new(ActorCell {
+        mailbox: Mailbox::new(),
+        actor: UnsafeCell::new(actor),
+        scheduled: AtomicBool::new(false),
+    });
+    Cap { sink: cell }
+}
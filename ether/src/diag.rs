@@ -0,0 +1,56 @@
+use crate::frame::TreeId;
+use crate::hub::Route;
+use crate::link::LinkState;
+
+#[cfg(all(feature = "log", feature = "defmt"))]
+compile_error!("features `log` and `defmt` are mutually exclusive");
+
+#[cfg(feature = "log")]
+macro_rules! diag_trace { ($($arg:tt)*) => { log::trace!($($arg)*) }; }
+#[cfg(feature = "log")]
+macro_rules! diag_debug { ($($arg:tt)*) => { log::debug!($($arg)*) }; }
+#[cfg(feature = "log")]
+macro_rules! diag_info { ($($arg:tt)*) => { log::info!($($arg)*) }; }
+#[cfg(feature = "log")]
+macro_rules! diag_warn { ($($arg:tt)*) => { log::warn!($($arg)*) }; }
+
+#[cfg(feature = "defmt")]
+macro_rules! diag_trace { ($($arg:tt)*) => { defmt::trace!($($arg)*) }; }
+#[cfg(feature = "defmt")]
+macro_rules! diag_debug { ($($arg:tt)*) => { defmt::debug!($($arg)*) }; }
+#[cfg(feature = "defmt")]
+macro_rules! diag_info { ($($arg:tt)*) => { defmt::info!($($arg)*) }; }
+#[cfg(feature = "defmt")]
+macro_rules! diag_warn { ($($arg:tt)*) => { defmt::warn!($($arg)*) }; }
+
+#[cfg(not(any(feature = "log", feature = "defmt")))]
+macro_rules! diag_trace { ($($arg:tt)*) => {}; }
+#[cfg(not(any(feature = "log", feature = "defmt")))]
+macro_rules! diag_debug { ($($arg:tt)*) => {}; }
+#[cfg(not(any(feature = "log", feature = "defmt")))]
+macro_rules! diag_info { ($($arg:tt)*) => {}; }
+#[cfg(not(any(feature = "log", feature = "defmt")))]
+macro_rules! diag_warn { ($($arg:tt)*) => {}; }
+
+pub(crate) use {diag_debug, diag_info, diag_trace, diag_warn};
+
+// Structured state transitions an observer `Cap<DiagEvent>` can subscribe to,
+// so link liveness and transfer accounting can be monitored without
+// scraping stdout.
+#[derive(Debug, Clone)]
+pub enum DiagEvent {
+    LinkState(LinkState, LinkState), // (from, to)
+    AitBalance(isize),               // new balance
+    Route(TreeId, Route, Vec<Route>), // (tree_id, from, send_to)
+}
+impl DiagEvent {
+    pub fn new_link_state(from: &LinkState, to: &LinkState) -> DiagEvent {
+        DiagEvent::LinkState(from.clone(), to.clone())
+    }
+    pub fn new_ait_balance(balance: isize) -> DiagEvent {
+        DiagEvent::AitBalance(balance)
+    }
+    pub fn new_route(tree_id: &TreeId, from: &Route, send_to: &[Route]) -> DiagEvent {
+        DiagEvent::Route(tree_id.clone(), from.clone(), send_to.to_vec())
+    }
+}
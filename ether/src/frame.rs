@@ -0,0 +1,118 @@
+// Wire-level framing for `Link`'s entanglement protocol.
+//
+// A reset frame carries just the initiator's nonce, used to tie-break
+// simultaneous opens. An entangled frame carries a liveness/AIT state
+// signal (`i_state`) plus an echo of the peer's last signal (`ack`), and
+// once AIT is underway, a sequence number and optionally a payload.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TreeId(u32);
+impl TreeId {
+    pub fn new(nonce: u32) -> TreeId {
+        TreeId(nonce)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Payload {
+    pub id: TreeId, // which multicast tree this payload belongs to
+    bytes: Vec<u8>,
+}
+impl Payload {
+    pub fn new(id: &TreeId, bytes: &[u8]) -> Payload {
+        Payload {
+            id: id.clone(),
+            bytes: bytes.to_vec(),
+        }
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Payload {
+        Payload {
+            id: TreeId::new(0),
+            bytes: bytes.to_vec(),
+        }
+    }
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+// i_state / ack values carried by an entangled frame
+pub const TICK: u8 = 0; // liveness, no AIT in flight
+pub const TECK: u8 = 1; // begin AIT: seq + payload attached
+pub const TACK: u8 = 2; // ack AIT: seq attached
+pub const RTECK: u8 = 3; // reject AIT: receive buffer was full
+
+#[derive(Debug, Clone)]
+enum FrameBody {
+    Reset {
+        nonce: u32,
+    },
+    Entangled {
+        tree_id: TreeId,
+        i_state: u8,
+        ack: u8,
+        seq: u8,
+        payload: Option<Payload>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Frame(FrameBody);
+impl Frame {
+    pub fn new_reset(nonce: u32) -> Frame {
+        Frame(FrameBody::Reset { nonce })
+    }
+    pub fn new_entangled(tree_id: &TreeId, i_state: u8, ack: u8) -> Frame {
+        Frame(FrameBody::Entangled {
+            tree_id: tree_id.clone(),
+            i_state,
+            ack,
+            seq: 0,
+            payload: None,
+        })
+    }
+    pub fn is_reset(&self) -> bool {
+        matches!(self.0, FrameBody::Reset { .. })
+    }
+    pub fn is_entangled(&self) -> bool {
+        matches!(self.0, FrameBody::Entangled { .. })
+    }
+    pub fn get_nonce(&self) -> u32 {
+        match &self.0 {
+            FrameBody::Reset { nonce } => *nonce,
+            FrameBody::Entangled { .. } => panic!("get_nonce on an entangled frame"),
+        }
+    }
+    pub fn get_i_state(&self) -> u8 {
+        match &self.0 {
+            FrameBody::Entangled { i_state, .. } => *i_state,
+            FrameBody::Reset { .. } => panic!("get_i_state on a reset frame"),
+        }
+    }
+    pub fn get_seq(&self) -> u8 {
+        match &self.0 {
+            FrameBody::Entangled { seq, .. } => *seq,
+            FrameBody::Reset { .. } => panic!("get_seq on a reset frame"),
+        }
+    }
+    pub fn set_seq(&mut self, seq: u8) {
+        match &mut self.0 {
+            FrameBody::Entangled { seq: s, .. } => *s = seq,
+            FrameBody::Reset { .. } => panic!("set_seq on a reset frame"),
+        }
+    }
+    pub fn get_payload(&self) -> Payload {
+        match &self.0 {
+            FrameBody::Entangled { payload, .. } => {
+                payload.clone().expect("frame carries no payload")
+            }
+            FrameBody::Reset { .. } => panic!("get_payload on a reset frame"),
+        }
+    }
+    pub fn set_payload(&mut self, payload: &Payload) {
+        match &mut self.0 {
+            FrameBody::Entangled { payload: p, .. } => *p = Some(payload.clone()),
+            FrameBody::Reset { .. } => panic!("set_payload on a reset frame"),
+        }
+    }
+}
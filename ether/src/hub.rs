@@ -1,6 +1,12 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, Once, OnceLock};
+use std::thread;
+use std::time::Duration;
+
 use crate::actor::{self, Actor, Cap};
 use crate::cell::CellEvent;
-use crate::frame::Payload;
+use crate::diag::{diag_debug, diag_info, diag_warn, DiagEvent};
+use crate::frame::{Payload, TreeId};
 use crate::port::{PortEvent, PortState};
 use crate::pollster::{Pollster, PollsterEvent};
 
@@ -12,6 +18,10 @@ pub enum HubEvent {
     PortToHubRead(Cap<PortEvent>),
     CellToHubWrite(Cap<CellEvent>, Payload),
     CellToHubRead(Cap<CellEvent>),
+    InstallRoute(TreeId, Route),
+    WithdrawRoute(TreeId, Route),
+    SetDiag(Cap<DiagEvent>),
+    PortFault(Cap<PortEvent>),
 }
 impl HubEvent {
     pub fn new_init(hub: &Cap<HubEvent>) -> HubEvent {
@@ -32,11 +42,24 @@ impl HubEvent {
     pub fn new_cell_to_hub_read(cell: &Cap<CellEvent>) -> HubEvent {
         HubEvent::CellToHubRead(cell.clone())
     }
+    pub fn new_install_route(tree_id: &TreeId, route: &Route) -> HubEvent {
+        HubEvent::InstallRoute(tree_id.clone(), route.clone())
+    }
+    pub fn new_withdraw_route(tree_id: &TreeId, route: &Route) -> HubEvent {
+        HubEvent::WithdrawRoute(tree_id.clone(), route.clone())
+    }
+    pub fn new_set_diag(diag: &Cap<DiagEvent>) -> HubEvent {
+        HubEvent::SetDiag(diag.clone())
+    }
+    pub fn new_port_fault(port: &Cap<PortEvent>) -> HubEvent {
+        HubEvent::PortFault(port.clone())
+    }
 }
 
 const MAX_PORTS: usize = 3;
 
-enum Route {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
     Cell,
     Port(usize),
 }
@@ -65,6 +88,45 @@ struct PortOut {
     reader: Option<Cap<PortEvent>>,
 }
 
+// --- shared heartbeat ----------------------------------------------------
+//
+// Every Hub needs a periodic liveness poll, but a dedicated OS thread per
+// Hub doesn't scale any better than a dedicated mailbox-draining thread per
+// actor did. One shared ticker drives every registered Hub's poll off a
+// single sleeping thread instead.
+struct Ticker {
+    targets: Mutex<Vec<(Cap<PollsterEvent>, Cap<HubEvent>)>>,
+}
+impl Ticker {
+    fn get() -> &'static Ticker {
+        static TICKER: OnceLock<Ticker> = OnceLock::new();
+        static STARTED: Once = Once::new();
+        let ticker = TICKER.get_or_init(|| Ticker {
+            targets: Mutex::new(Vec::new()),
+        });
+        STARTED.call_once(|| {
+            thread::spawn(Ticker::tick_loop);
+        });
+        ticker
+    }
+    fn register(&self, pollster: Cap<PollsterEvent>, hub: Cap<HubEvent>) {
+        self.targets
+            .lock()
+            .expect("ticker targets poisoned")
+            .push((pollster, hub));
+    }
+    fn tick_loop() {
+        let ticker = Ticker::get();
+        loop {
+            thread::sleep(Duration::from_millis(500));
+            let targets = ticker.targets.lock().expect("ticker targets poisoned");
+            for (pollster, hub) in targets.iter() {
+                pollster.send(PollsterEvent::new_poll(hub));
+            }
+        }
+    }
+}
+
 // Multi-Port Hub (Node)
 pub struct Hub {
     myself: Option<Cap<HubEvent>>,
@@ -73,6 +135,9 @@ pub struct Hub {
     cell_out: CellOut,
     port_in: Vec<PortIn>,
     port_out: Vec<PortOut>,
+    routes: HashMap<TreeId, Vec<Route>>,
+    diag: Option<Cap<DiagEvent>>,
+    down: Vec<bool>, // quarantined ports, dropped from routing and no longer serviced
 }
 impl Hub {
     pub fn create(port_set: &[Cap<PortEvent>]) -> Cap<HubEvent> {
@@ -106,21 +171,20 @@ impl Hub {
             cell_out,
             port_in,
             port_out,
+            routes: HashMap::new(),
+            diag: None,
+            down: vec![false; ports.len()],
         });
         hub.send(HubEvent::new_init(&hub));
         for port in port_set {
+            port.send(PortEvent::new_set_hub(&hub)); // late-bind the back-reference for fault reporting
             port.send(PortEvent::new_hub_to_port_read(&hub)); // Port ready to receive
         }
         let pollster = Pollster::create(&ports); // create link-failure detector
         pollster.send(PollsterEvent::new_start(&hub));
-        // periodically poll ports for liveness
-        let cust = hub.clone(); // local copy moved into closure
-        std::thread::spawn(move || {
-            loop {
-                std::thread::sleep(core::time::Duration::from_millis(500));
-                pollster.send(PollsterEvent::new_poll(&cust));
-            }
-        });
+        // drive this Hub's periodic liveness poll off the shared ticker
+        // thread instead of spawning a dedicated OS thread per Hub
+        Ticker::get().register(pollster, hub.clone());
         // return Hub capability
         hub
     }
@@ -136,59 +200,82 @@ impl Actor for Hub {
             },
             HubEvent::PortStatus(cust, state) => {
                 let n = self.port_to_port_num(&cust);
-                println!(
+                diag_info!(
                     "Hub::LinkStatus[{}] link_state={:?}, ait_balance={}",
                     n, state.link_state, state.ait_balance
                 );
             }
             HubEvent::PortToHubWrite(cust, payload) => {
-                println!("Hub::PortToHubWrite");
+                diag_debug!("Hub::PortToHubWrite");
                 let n = self.port_to_port_num(&cust);
-                let port_in = &mut self.port_in[n];
-                match &port_in.writer {
-                    None => {
-                        port_in.writer = Some(cust.clone());
-                        port_in.payload = Some(payload.clone());
-                        self.find_routes(Route::Port(n), &payload);
-                        self.try_everyone();
-                    }
-                    Some(_cust) => panic!("Only one Port-to-Hub writer allowed"),
+                if self.down[n] {
+                    return; // port is quarantined; drop its traffic
                 }
+                let is_second_writer = self.port_in[n].writer.is_some();
+                if is_second_writer {
+                    diag_warn!("Hub::PortToHubWrite[{}] second concurrent writer", n);
+                    self.quarantine_port(n);
+                    return;
+                }
+                let port_in = &mut self.port_in[n];
+                port_in.writer = Some(cust.clone());
+                port_in.payload = Some(payload.clone());
+                self.find_routes(Route::Port(n), &payload);
+                self.try_everyone();
             }
             HubEvent::PortToHubRead(cust) => {
-                println!("Hub::PortToHubRead");
+                diag_debug!("Hub::PortToHubRead");
                 let n = self.port_to_port_num(&cust);
-                let port_out = &mut self.port_out[n];
-                match &port_out.reader {
-                    None => {
-                        port_out.reader = Some(cust.clone());
-                        self.try_everyone();
-                    }
-                    Some(_cust) => panic!("Only one Port-to-Hub reader allowed"),
+                if self.down[n] {
+                    return; // port is quarantined; drop its traffic
+                }
+                let is_second_reader = self.port_out[n].reader.is_some();
+                if is_second_reader {
+                    diag_warn!("Hub::PortToHubRead[{}] second concurrent reader", n);
+                    self.quarantine_port(n);
+                    return;
                 }
+                self.port_out[n].reader = Some(cust.clone());
+                self.try_everyone();
             }
             HubEvent::CellToHubWrite(cust, payload) => {
-                println!("Hub::CellToHubWrite");
-                match &self.cell_out.writer {
-                    None => {
-                        self.cell_out.writer = Some(cust.clone());
-                        self.cell_out.payload = Some(payload.clone());
-                        self.find_routes(Route::Cell, &payload);
-                        self.try_everyone();
-                    }
-                    Some(_cust) => panic!("Only one Cell-to-Hub writer allowed"),
+                diag_debug!("Hub::CellToHubWrite");
+                if self.cell_out.writer.is_some() {
+                    diag_warn!("Hub::CellToHubWrite second concurrent writer; dropped");
+                    return;
                 }
+                self.cell_out.writer = Some(cust.clone());
+                self.cell_out.payload = Some(payload.clone());
+                self.find_routes(Route::Cell, &payload);
+                self.try_everyone();
             }
             HubEvent::CellToHubRead(cust) => {
-                println!("Hub::CellToHubRead");
-                match &self.cell_in.reader {
-                    None => {
-                        self.cell_in.reader = Some(cust.clone());
-                        self.try_everyone();
-                    }
-                    Some(_cust) => panic!("Only one Cell-to-Hub reader allowed"),
+                diag_debug!("Hub::CellToHubRead");
+                if self.cell_in.reader.is_some() {
+                    diag_warn!("Hub::CellToHubRead second concurrent reader; dropped");
+                    return;
+                }
+                self.cell_in.reader = Some(cust.clone());
+                self.try_everyone();
+            }
+            HubEvent::InstallRoute(tree_id, route) => {
+                let routes = self.routes.entry(tree_id.clone()).or_insert_with(Vec::new);
+                if !routes.contains(route) {
+                    routes.push(route.clone());
+                }
+            }
+            HubEvent::WithdrawRoute(tree_id, route) => {
+                if let Some(routes) = self.routes.get_mut(tree_id) {
+                    routes.retain(|r| r != route);
                 }
             }
+            HubEvent::SetDiag(diag) => {
+                self.diag = Some(diag.clone());
+            }
+            HubEvent::PortFault(cust) => {
+                let n = self.port_to_port_num(&cust);
+                self.quarantine_port(n);
+            }
         }
     }
 }
@@ -201,20 +288,68 @@ impl Hub {
             .expect("unknown Port")
             .0
     }
+    // Drop a misbehaving port's routes and stop servicing it, without taking
+    // down the rest of the fabric. Intentionally permanent: there is no
+    // un-quarantine path, and `down[n]` is never cleared once set. A port
+    // that legitimately recovers (e.g. a cable replaced, a peer rebooted)
+    // needs a brand new Port/Link pair built by the caller and passed to
+    // `Hub::create`; an existing Hub has no way to add a port to `ports`.
+    // Quarantine is a one-way breaker, not a retry budget.
+    fn quarantine_port(&mut self, n: usize) {
+        if self.down[n] {
+            return; // already quarantined
+        }
+        diag_warn!("Hub::quarantine_port[{}]", n);
+        self.down[n] = true;
+        for routes in self.routes.values_mut() {
+            routes.retain(|route| *route != Route::Port(n));
+        }
+        // also purge the dead port from any fan-out already computed for an
+        // in-flight write; otherwise that writer's send_to never empties and
+        // it waits forever on a route that can never complete
+        self.cell_out.send_to.retain(|route| *route != Route::Port(n));
+        for port_in in self.port_in.iter_mut() {
+            port_in.send_to.retain(|route| *route != Route::Port(n));
+        }
+        self.port_in[n] = PortIn {
+            writer: None,
+            payload: None,
+            send_to: Vec::new(),
+        };
+        self.port_out[n] = PortOut { reader: None };
+        // a purge may have just emptied someone's send_to; drive it forward
+        self.try_everyone();
+    }
     fn find_routes(&mut self, from: Route, payload: &Payload) {
-        // FIXME: this is a completely bogus "routing table" lookup!
-        // The TreeId in the Payload should determine the routes, excluding `from`.
-        let _tree_id = &payload.id;
+        // Look up the subscribers for this tree, fanning out to everyone
+        // except the ingress route the payload arrived on and any
+        // quarantined ports.
+        let down = &self.down;
+        let send_to: Vec<Route> = self
+            .routes
+            .get(&payload.id)
+            .map(|routes| {
+                routes
+                    .iter()
+                    .filter(|route| **route != from)
+                    .filter(|route| !matches!(route, Route::Port(n) if down[*n]))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let Some(diag) = &self.diag {
+            diag.send(DiagEvent::new_route(&payload.id, &from, &send_to));
+        }
         match from {
             Route::Cell => {
                 let routes = &mut self.cell_out.send_to;
                 assert!(routes.is_empty()); // there shouldn't be any left-over routes
-                routes.push(Route::Port(0)); // all Cell tokens route to Port(0)
+                *routes = send_to;
             }
             Route::Port(n) => {
                 let routes = &mut self.port_in[n].send_to;
                 assert!(routes.is_empty()); // there shouldn't be any left-over routes
-                routes.push(Route::Cell); // all Port(_) tokens route to Cell
+                *routes = send_to;
             }
         }
     }
@@ -1,9 +1,27 @@
+use std::collections::VecDeque;
+
 use crate::actor::{self, Actor, Cap};
+use crate::diag::{diag_debug, diag_warn, DiagEvent};
 use crate::frame::{self, Frame, Payload, TreeId};
 use crate::port::PortEvent;
 use crate::wire::WireEvent;
 use rand::Rng;
 
+// default send/receive window (W): max outstanding AIT payloads per direction
+const DEFAULT_WINDOW: isize = 4;
+
+// bound handshake retransmission: report failure, and restart the handshake
+// with a fresh nonce, after this many poll intervals spent stuck in Init
+// without reaching Run. This does
+// not distinguish a cold-start peer that's merely slow to come up from one
+// that's genuinely unreachable or misbehaving: both exhaust retries the
+// same way and both get reported as a `PortFault`, which `Hub` quarantines
+// permanently (see `Hub::quarantine_port`). That's an accepted trade-off
+// here, not an oversight: a fabric that can wedge on "slow to start" is
+// worse than one that occasionally over-quarantines a cold link, and the
+// retry budget below is tuned to give a normal handshake ample time first.
+const MAX_HANDSHAKE_RETRIES: u32 = 5;
+
 #[derive(Debug, Clone)]
 pub enum LinkEvent {
     Frame(Frame),                   // inbound frame received
@@ -12,6 +30,7 @@ pub enum LinkEvent {
     Stop(Cap<PortEvent>),           // stop link activity
     Read(Cap<PortEvent>),           // reader ready
     Write(Cap<PortEvent>, Payload), // writer full
+    SetDiag(Cap<DiagEvent>),        // subscribe an observer to diagnostics
 }
 impl LinkEvent {
     pub fn new_frame(frame: &Frame) -> LinkEvent {
@@ -32,6 +51,9 @@ impl LinkEvent {
     pub fn new_write(port: &Cap<PortEvent>, payload: &Payload) -> LinkEvent {
         LinkEvent::Write(port.clone(), payload.clone())
     }
+    pub fn new_set_diag(diag: &Cap<DiagEvent>) -> LinkEvent {
+        LinkEvent::SetDiag(diag.clone())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -44,27 +66,104 @@ pub enum LinkState {
 
 pub struct Link {
     wire: Cap<WireEvent>,
+    port: Option<Cap<PortEvent>>, // the Port this Link reports faults to
     nonce: u32,
     state: LinkState,
-    balance: isize,
+    window: isize,                     // W: configurable send/receive window
+    balance: isize,                    // in-flight credit, ranges over [-W, +W]
+    next_tx_seq: u8,                   // next sequence number to assign on Write
+    outbound: VecDeque<(u8, Payload)>, // ring of accepted-but-unacked outbound payloads
     reader: Option<Cap<PortEvent>>,
-    inbound: Option<Payload>,
-    writer: Option<Cap<PortEvent>>,
-    outbound: Option<Payload>,
+    inbound: VecDeque<(u8, Payload)>, // payloads received, queued for in-order release
+    next_rx_seq: u8,                  // next sequence number expected by the port
+    writer: Option<Cap<PortEvent>>,   // Port blocked on Write because the window is full
+    pending_write: Option<Payload>,   // payload waiting for room in `outbound`
+    diag: Option<Cap<DiagEvent>>,     // optional observer for structured diagnostics
+    retry_count: u32,                 // poll intervals spent stuck in Init
 }
 impl Link {
     pub fn create(wire: &Cap<WireEvent>, nonce: u32) -> Cap<LinkEvent> {
+        Self::create_with_window(wire, nonce, DEFAULT_WINDOW)
+    }
+    pub fn create_with_window(wire: &Cap<WireEvent>, nonce: u32, window: isize) -> Cap<LinkEvent> {
+        assert!(window > 0, "window must be positive");
         actor::create(Link {
             wire: wire.clone(),
+            port: None,
             nonce,
             state: LinkState::Stop,
+            window,
             balance: 0,
+            next_tx_seq: 0,
+            outbound: VecDeque::new(),
             reader: None,
-            inbound: None,
+            inbound: VecDeque::new(),
+            next_rx_seq: 0,
             writer: None,
-            outbound: None,
+            pending_write: None,
+            diag: None,
+            retry_count: 0,
         })
     }
+    // transition `state`, emitting a structured diagnostic event on change
+    fn set_state(&mut self, state: LinkState) {
+        if state != LinkState::Init {
+            self.retry_count = 0; // handshake (if any) converged
+        }
+        if state != self.state {
+            if let Some(diag) = &self.diag {
+                diag.send(DiagEvent::new_link_state(&self.state, &state));
+            }
+            self.state = state;
+        }
+    }
+    // update `balance`, emitting a structured diagnostic event on change
+    fn set_balance(&mut self, balance: isize) {
+        if balance != self.balance {
+            self.balance = balance;
+            if let Some(diag) = &self.diag {
+                diag.send(DiagEvent::new_ait_balance(self.balance));
+            }
+        }
+    }
+    // contain a protocol fault instead of taking down the whole process:
+    // reset to Init with a fresh nonce, re-send the reset, and report the
+    // fault to our Port (similar in spirit to a poisoned lock being detected
+    // and reset rather than propagated)
+    fn contain_fault(&mut self, reason: &str) {
+        diag_warn!("Link::fault: {}", reason);
+        self.nonce = rand::thread_rng().gen();
+        self.retry_count = 0; // starting a fresh handshake attempt
+        self.set_state(LinkState::Init);
+        let reset = Frame::new_reset(self.nonce);
+        self.wire.send(WireEvent::new_frame(&reset));
+        if let Some(port) = &self.port {
+            port.send(PortEvent::new_link_fault());
+        }
+    }
+    // next outbound entry not yet sent as a TECK, if any and credit remains
+    fn next_to_send(&self) -> Option<&(u8, Payload)> {
+        if self.balance <= -self.window {
+            return None; // window full
+        }
+        self.outbound.get((-self.balance) as usize)
+    }
+    // deliver in-order inbound payloads to the reader as it becomes ready
+    fn release_inbound(&mut self) {
+        while let Some((seq, _)) = self.inbound.front() {
+            if *seq != self.next_rx_seq {
+                break; // out of order; wait for the missing sequence number
+            }
+            match self.reader.take() {
+                Some(reader) => {
+                    let (_seq, payload) = self.inbound.pop_front().expect("front already peeked");
+                    reader.send(PortEvent::new_link_to_port_write(&payload));
+                    self.next_rx_seq = self.next_rx_seq.wrapping_add(1);
+                }
+                None => break, // no reader ready yet
+            }
+        }
+    }
 }
 impl Actor for Link {
     type Event = LinkEvent;
@@ -76,44 +175,30 @@ impl Actor for Link {
                 if self.state == LinkState::Stop {
                     return; // EARLY EXIT WHEN LINK IS STOPPED.
                 } else if frame.is_reset() {
-                    self.state = LinkState::Init;
+                    self.set_state(LinkState::Init);
                     let nonce = frame.get_nonce();
-                    println!("Link::nonce={}, frame.nonce={}", self.nonce, nonce);
+                    diag_debug!("Link::nonce={}, frame.nonce={}", self.nonce, nonce);
                     if self.nonce < nonce {
-                        println!("waiting...");
+                        diag_debug!("waiting...");
                     } else if self.nonce > nonce {
-                        println!("entangle...");
+                        diag_debug!("entangle...");
                         let reply = Frame::new_entangled(&tree_id, frame::TICK, frame::TICK);
                         self.wire.send(WireEvent::new_frame(&reply));
                     } else {
-                        println!("collision...");
+                        diag_debug!("collision...");
                         self.nonce = rand::thread_rng().gen();
                         let reply = Frame::new_reset(self.nonce);
                         self.wire.send(WireEvent::new_frame(&reply));
                     }
                 } else if frame.is_entangled() {
-                    self.state = LinkState::Live;
+                    self.set_state(LinkState::Live);
                     let i_state = frame.get_i_state();
-                    //println!("entangled i={}", i_state);
+                    //diag_trace!("entangled i={}", i_state);
                     match i_state {
                         frame::TICK => {
-                            //println!("TICK rcvd."); // liveness recv'd
-                            if self.balance == 1 {
-                                // receive completed
-                                println!("TICK w/ surplus");
-                                if let Some(reader) = &self.reader {
-                                    if let Some(payload) = &self.inbound {
-                                        reader.send( // release payload
-                                            PortEvent::new_link_to_port_write(&payload)
-                                        );
-                                        self.reader = None; // reader satisfied
-                                        self.inbound = None; // clear inbound
-                                        self.balance = 0; // clear balance
-                                    }
-                                }
-                            }
-                            assert_eq!(self.balance, 0); // at this point, the balance should always be 0
-                            match &self.outbound {
+                            //diag_trace!("TICK rcvd."); // liveness recv'd
+                            self.release_inbound(); // deliver any in-order payloads we can
+                            match self.next_to_send() {
                                 None => {
                                     let reply = Frame::new_entangled(
                                         &tree_id,
@@ -122,109 +207,144 @@ impl Actor for Link {
                                     );
                                     self.wire.send(WireEvent::new_frame(&reply));
                                 }
-                                Some(payload) => {
+                                Some((seq, payload)) => {
+                                    diag_debug!("TICK w/ surplus");
                                     let mut reply = Frame::new_entangled(
                                         &tree_id,
                                         frame::TECK, // begin AIT
                                         i_state,
                                     );
-                                    reply.set_payload(&payload);
+                                    reply.set_seq(*seq);
+                                    reply.set_payload(payload);
                                     self.wire.send(WireEvent::new_frame(&reply));
-                                    self.balance = -1; // deficit balance
+                                    self.set_balance(self.balance - 1); // another payload in flight
                                 }
                             }
                         }
                         frame::TECK => {
-                            println!("TECK rcvd."); // begin AIT recv'd
-                            match &self.reader {
-                                Some(_cust) => {
-                                    // reader ready
-                                    self.inbound = Some(frame.get_payload());
+                            diag_debug!("TECK rcvd."); // begin AIT recv'd
+                            let seq = frame.get_seq();
+                            if self.inbound.len() < self.window as usize {
+                                // receive buffer has room
+                                self.inbound.push_back((seq, frame.get_payload()));
+                                let mut reply = Frame::new_entangled(
+                                    &tree_id,
+                                    frame::TACK, // Ack AIT
+                                    i_state,
+                                );
+                                reply.set_seq(seq);
+                                self.wire.send(WireEvent::new_frame(&reply));
+                                self.release_inbound();
+                            } else {
+                                // receive buffer is genuinely full
+                                let mut reply = Frame::new_entangled(
+                                    &tree_id,
+                                    frame::RTECK, // reject AIT
+                                    i_state,
+                                );
+                                reply.set_seq(seq);
+                                self.wire.send(WireEvent::new_frame(&reply));
+                            }
+                        }
+                        frame::TACK => {
+                            diag_debug!("TACK rcvd."); // Ack AIT recv'd
+                            let seq = frame.get_seq();
+                            match self.outbound.front() {
+                                Some((front_seq, _)) if *front_seq == seq => {
+                                    diag_debug!("TACK w/ deficit");
+                                    self.outbound.pop_front();
+                                    self.set_balance(self.balance + 1); // writer slot released
+                                    if self.outbound.len() < self.window as usize {
+                                        if let Some(payload) = self.pending_write.take() {
+                                            self.outbound.push_back((self.next_tx_seq, payload));
+                                            self.next_tx_seq = self.next_tx_seq.wrapping_add(1);
+                                        }
+                                        if let Some(writer) = self.writer.take() {
+                                            writer.send(PortEvent::new_link_to_port_read()); // acknowlege write
+                                        }
+                                    }
                                     let reply = Frame::new_entangled(
                                         &tree_id,
-                                        frame::TACK, // Ack AIT
+                                        frame::TICK, // liveness (Ack Ack)
                                         i_state,
                                     );
                                     self.wire.send(WireEvent::new_frame(&reply));
-                                    self.balance = 1; // surplus balance
                                 }
-                                None => {
-                                    // no reader ready
-                                    let reply = Frame::new_entangled(
-                                        &tree_id,
-                                        frame::RTECK, // reject AIT
-                                        i_state,
-                                    );
-                                    self.wire.send(WireEvent::new_frame(&reply));
-                                    //self.balance = 0; // balance already clear?
-                                    assert_eq!(self.balance, 0);
+                                _ => {
+                                    diag_debug!("TACK w/ unexpected seq={}", seq);
                                 }
                             }
                         }
-                        frame::TACK => {
-                            println!("TACK rcvd."); // Ack AIT recv'd
-                            assert_eq!(self.balance, -1); // deficit expected
-                            println!("TACK w/ deficit");
-                            if let Some(writer) = &self.writer {
-                                writer.send(PortEvent::new_link_to_port_read()); // acknowlege write
-                                self.writer = None; // writer satisfied
-                                self.outbound = None; // clear outbound
-                                self.balance = 0; // clear balance
-                                let reply = Frame::new_entangled(
-                                    &tree_id,
-                                    frame::TICK, // liveness (Ack Ack)
-                                    i_state,
-                                );
-                                self.wire.send(WireEvent::new_frame(&reply));
-                            }
-                        }
                         frame::RTECK => {
-                            println!("RTECK rcvd."); // Reject AIT recv'd
+                            diag_debug!("RTECK rcvd."); // Reject AIT recv'd
                             let reply = Frame::new_entangled(
                                 &tree_id,
                                 frame::TICK, // liveness
                                 i_state,
                             );
                             self.wire.send(WireEvent::new_frame(&reply));
-                            self.balance = 0; // clear deficit
+                            self.set_balance(self.balance + 1); // rejected payload stays queued to retry
                         }
                         _ => {
-                            panic!("bad protocol state");
+                            self.contain_fault("bad protocol state");
                         }
                     }
                 } else {
-                    panic!("bad frame format");
+                    self.contain_fault("bad frame format");
                 }
             }
             LinkEvent::Start(cust) => {
+                self.port = Some(cust.clone());
                 let init = Frame::new_reset(self.nonce);
                 self.wire.send(WireEvent::new_frame(&init)); // send init/reset
-                self.state = LinkState::Init;
+                self.set_state(LinkState::Init);
                 cust.send(PortEvent::new_link_status(&self.state, &self.balance));
             }
             LinkEvent::Poll(cust) => {
                 cust.send(PortEvent::new_link_status(&self.state, &self.balance));
                 if self.state == LinkState::Live {
-                    self.state = LinkState::Run; // clear Live status
+                    self.set_state(LinkState::Run); // clear Live status
+                } else if self.state == LinkState::Init {
+                    // no progress since the last poll; a reset frame may
+                    // have been lost, so resend it, bounding retries
+                    self.retry_count += 1;
+                    if self.retry_count > MAX_HANDSHAKE_RETRIES {
+                        self.contain_fault("handshake did not converge");
+                    } else {
+                        let reset = Frame::new_reset(self.nonce);
+                        self.wire.send(WireEvent::new_frame(&reset));
+                    }
                 }
             }
             LinkEvent::Stop(cust) => {
-                self.state = LinkState::Stop;
+                self.set_state(LinkState::Stop);
                 cust.send(PortEvent::new_link_status(&self.state, &self.balance));
             }
             LinkEvent::Read(cust) => match &self.reader {
                 None => {
                     self.reader = Some(cust.clone());
+                    self.release_inbound();
                 }
                 Some(_cust) => panic!("Only one Link-to-Port reader allowed"),
             },
-            LinkEvent::Write(cust, payload) => match &self.writer {
-                None => {
-                    self.outbound = Some(payload.clone());
+            LinkEvent::Write(cust, payload) => {
+                if self.writer.is_some() {
+                    panic!("Only one Port-to-Link writer allowed");
+                }
+                if self.outbound.len() < self.window as usize {
+                    // window has room; accept the payload right away
+                    self.outbound.push_back((self.next_tx_seq, payload.clone()));
+                    self.next_tx_seq = self.next_tx_seq.wrapping_add(1);
+                    cust.send(PortEvent::new_link_to_port_read()); // acknowlege write
+                } else {
+                    // window is full; hold the writer until a TACK frees a slot
                     self.writer = Some(cust.clone());
+                    self.pending_write = Some(payload.clone());
                 }
-                Some(_cust) => panic!("Only one Port-to-Link writer allowed"),
-            },
+            }
+            LinkEvent::SetDiag(diag) => {
+                self.diag = Some(diag.clone());
+            }
         }
     }
 }
@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use embassy_net_driver::{Capabilities, Driver, LinkState as DriverLinkState, Medium};
+
+use crate::actor::{self, Actor, Cap};
+use crate::frame::Payload;
+use crate::link::{LinkEvent, LinkState};
+use crate::port::PortEvent;
+
+// Shared state between the actor side (fed by DevicePort deliveries) and the
+// embassy-net/smoltcp side (polled by `Device`).
+struct Shared {
+    rx_queue: VecDeque<Payload>,
+    link_state: DriverLinkState,
+    ait_balance: isize,
+    window: isize,
+}
+
+// A lightweight PortEvent sink standing in for a full `Port`: it drives
+// `Shared` directly instead of round-tripping through Port's crossbeam
+// tx/rx channels, which a `Device` has no reader/writer attached to. Keeps
+// the inbound flow armed by re-issuing `LinkEvent::Read` after every
+// delivered payload, the way `Port` re-requests via its own `myself`.
+struct DevicePort {
+    myself: Option<Cap<PortEvent>>,
+    link: Cap<LinkEvent>,
+    shared: Arc<Mutex<Shared>>,
+}
+impl Actor for DevicePort {
+    type Event = PortEvent;
+
+    fn on_event(&mut self, event: Self::Event) {
+        match event {
+            PortEvent::Init(myself) => {
+                self.link.send(LinkEvent::new_read(&myself)); // arm the inbound flow
+                self.myself = Some(myself);
+            }
+            PortEvent::SetHub(_) => {} // a Device has no Hub to report faults to
+            PortEvent::LinkStatus(state, balance) => {
+                let mut shared = self.shared.lock().expect("net_driver shared state poisoned");
+                shared.link_state = match state {
+                    LinkState::Run | LinkState::Live => DriverLinkState::Up,
+                    LinkState::Stop | LinkState::Init => DriverLinkState::Down,
+                };
+                shared.ait_balance = balance;
+            }
+            PortEvent::LinkToPortWrite(payload) => {
+                self.shared
+                    .lock()
+                    .expect("net_driver shared state poisoned")
+                    .rx_queue
+                    .push_back(payload);
+                if let Some(myself) = &self.myself {
+                    self.link.send(LinkEvent::new_read(myself)); // re-arm for the next payload
+                }
+            }
+            PortEvent::LinkToPortRead => {} // Link acking a TX write; nothing to do
+            PortEvent::LinkFault => {} // fault routing isn't wired for Device (no Hub)
+        }
+    }
+}
+
+// A Hub port exposed as an embassy-net `Driver`, so smoltcp can carry real IP
+// traffic over the link fabric without touching raw actor events.
+pub struct Device {
+    link: Cap<LinkEvent>,
+    port: Cap<PortEvent>,
+    shared: Arc<Mutex<Shared>>,
+    mtu: usize,
+}
+impl Device {
+    pub fn create(link: &Cap<LinkEvent>, mtu: usize, window: isize) -> Device {
+        let shared = Arc::new(Mutex::new(Shared {
+            rx_queue: VecDeque::new(),
+            link_state: DriverLinkState::Down,
+            ait_balance: 0,
+            window,
+        }));
+        let port = actor::create(DevicePort {
+            myself: None,
+            link: link.clone(),
+            shared: shared.clone(),
+        });
+        port.send(PortEvent::new_init(&port));
+        Device {
+            link: link.clone(),
+            port,
+            shared,
+            mtu,
+        }
+    }
+}
+
+pub struct RxToken {
+    payload: Payload,
+}
+impl embassy_net_driver::RxToken for RxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut buffer = self.payload.into_bytes();
+        f(&mut buffer)
+    }
+}
+
+pub struct TxToken {
+    link: Cap<LinkEvent>,
+    port: Cap<PortEvent>,
+}
+impl embassy_net_driver::TxToken for TxToken {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut buffer = vec![0u8; len];
+        let result = f(&mut buffer);
+        let payload = Payload::from_bytes(&buffer);
+        self.link.send(LinkEvent::new_write(&self.port, &payload));
+        result
+    }
+}
+
+impl Driver for Device {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken;
+
+    fn receive(&mut self, _cx: &mut core::task::Context) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let mut shared = self.shared.lock().expect("net_driver shared state poisoned");
+        if shared.link_state != DriverLinkState::Up {
+            return None;
+        }
+        let payload = shared.rx_queue.pop_front()?;
+        Some((
+            RxToken { payload },
+            TxToken {
+                link: self.link.clone(),
+                port: self.port.clone(),
+            },
+        ))
+    }
+
+    fn transmit(&mut self, _cx: &mut core::task::Context) -> Option<Self::TxToken<'_>> {
+        let shared = self.shared.lock().expect("net_driver shared state poisoned");
+        if shared.link_state != DriverLinkState::Up {
+            return None; // link is down
+        }
+        if shared.ait_balance <= -shared.window {
+            return None; // AIT window is full; apply back-pressure
+        }
+        Some(TxToken {
+            link: self.link.clone(),
+            port: self.port.clone(),
+        })
+    }
+
+    fn link_state(&mut self, _cx: &mut core::task::Context) -> DriverLinkState {
+        self.shared.lock().expect("net_driver shared state poisoned").link_state
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let mut caps = Capabilities::default();
+        caps.max_transmission_unit = self.mtu;
+        caps.medium = Medium::Ip;
+        caps
+    }
+
+    fn hardware_address(&self) -> embassy_net_driver::HardwareAddress {
+        embassy_net_driver::HardwareAddress::Ip
+    }
+}
@@ -1,5 +1,7 @@
 use crate::actor::{self, Actor, Cap};
+use crate::diag::{diag_info, diag_warn};
 use crate::frame::Payload;
+use crate::hub::HubEvent;
 use crate::link::{LinkEvent, LinkState};
 
 //use pretty_hex::pretty_hex;
@@ -9,14 +11,19 @@ use crossbeam::crossbeam_channel::{Receiver, Sender};
 #[derive(Debug, Clone)]
 pub enum PortEvent {
     Init(Cap<PortEvent>),
+    SetHub(Cap<HubEvent>), // late-bound back-reference, set once the Hub exists
     LinkStatus(LinkState, isize),
     LinkToPortWrite(Payload),
     LinkToPortRead,
+    LinkFault, // the Link hit a protocol fault and reset itself
 }
 impl PortEvent {
     pub fn new_init(port: &Cap<PortEvent>) -> PortEvent {
         PortEvent::Init(port.clone())
     }
+    pub fn new_set_hub(hub: &Cap<HubEvent>) -> PortEvent {
+        PortEvent::SetHub(hub.clone())
+    }
     pub fn new_link_status(state: &LinkState, balance: &isize) -> PortEvent {
         PortEvent::LinkStatus(state.clone(), balance.clone())
     }
@@ -26,6 +33,9 @@ impl PortEvent {
     pub fn new_link_to_port_read() -> PortEvent {
         PortEvent::LinkToPortRead
     }
+    pub fn new_link_fault() -> PortEvent {
+        PortEvent::LinkFault
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -37,10 +47,15 @@ pub struct PortState {
 pub struct Port {
     myself: Option<Cap<PortEvent>>,
     link: Cap<LinkEvent>,
+    hub: Option<Cap<HubEvent>>, // reports a faulted Link's quarantine upstream, once known
     tx: Sender<Payload>,
     rx: Receiver<Payload>,
 }
 impl Port {
+    // `hub` isn't known yet at construction time: Hub::create needs this
+    // Port's Cap to already exist before it can run, so the back-reference
+    // is late-bound via `PortEvent::SetHub` instead, mirroring how Hub binds
+    // its own `myself` through a self-sent `Init`.
     pub fn create(
         link: &Cap<LinkEvent>,
         tx: &Sender<Payload>,
@@ -49,6 +64,7 @@ impl Port {
         let port = actor::create(Port {
             myself: None,
             link: link.clone(),
+            hub: None,
             tx: tx.clone(),
             rx: rx.clone(),
         });
@@ -65,8 +81,17 @@ impl Actor for Port {
                 None => self.myself = Some(myself.clone()),
                 Some(_) => panic!("Port::myself already set"),
             },
+            PortEvent::SetHub(hub) => {
+                self.hub = Some(hub.clone());
+            }
             PortEvent::LinkStatus(state, balance) => {
-                println!("Port::LinkStatus state={:?}, balance={}", state, balance);
+                diag_info!("Port::LinkStatus state={:?}, balance={}", state, balance);
+            }
+            PortEvent::LinkFault => {
+                diag_warn!("Port::LinkFault - link reset itself after a protocol violation");
+                if let (Some(myself), Some(hub)) = (&self.myself, &self.hub) {
+                    hub.send(HubEvent::new_port_fault(myself));
+                }
             }
             PortEvent::LinkToPortWrite(payload) => {
                 //println!("Port::LinkToPortWrite");